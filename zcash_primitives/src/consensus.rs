@@ -3,7 +3,7 @@
 use std::cmp::{Ord, Ordering};
 use std::convert::TryFrom;
 use std::fmt;
-use std::ops::{Add, Sub};
+use std::ops::{Add, Bound, Sub};
 
 use crate::constants;
 
@@ -135,6 +135,51 @@ pub trait Parameters: Clone {
         self.activation_height(nu).map_or(false, |h| h <= height)
     }
 
+    /// Returns the range of block heights over which the consensus rules specific to
+    /// the given network upgrade are in force, or `None` if `nu` is not activated on
+    /// this network.
+    ///
+    /// The lower bound is the activation height of `nu` itself; the upper bound is the
+    /// activation height of the next upgrade that has one set, or unbounded if `nu` is
+    /// the last active upgrade.
+    ///
+    /// Returns a concrete `(Bound<BlockHeight>, Bound<BlockHeight>)` rather than
+    /// `impl RangeBounds<BlockHeight>` so this method doesn't depend on return-position
+    /// `impl Trait` in traits (stabilized in Rust 1.75), keeping it usable on older
+    /// toolchains.
+    ///
+    /// `nu` is expected to be a member of `UPGRADES_IN_ORDER`; for an upgrade outside
+    /// that list (e.g. `NetworkUpgrade::ZFuture`, which has no fixed position in the
+    /// activation sequence), the upper bound is always `Bound::Unbounded`, regardless of
+    /// any upgrade activated after it.
+    fn activation_range(
+        &self,
+        nu: NetworkUpgrade,
+    ) -> Option<(Bound<BlockHeight>, Bound<BlockHeight>)> {
+        let lower = self.activation_height(nu)?;
+        let upper = UPGRADES_IN_ORDER
+            .iter()
+            .skip_while(|&&upgrade| upgrade != nu)
+            .skip(1)
+            .find_map(|&upgrade| self.activation_height(upgrade));
+
+        Some((
+            Bound::Included(lower),
+            upper.map_or(Bound::Unbounded, Bound::Excluded),
+        ))
+    }
+
+    /// Returns the next network upgrade to activate after the given height, along with
+    /// its activation height, or `None` if no upgrade is scheduled to activate after
+    /// that height on this network.
+    fn next_activation(&self, height: BlockHeight) -> Option<(NetworkUpgrade, BlockHeight)> {
+        UPGRADES_IN_ORDER.iter().find_map(|&nu| {
+            self.activation_height(nu)
+                .filter(|&h| h > height)
+                .map(|h| (nu, h))
+        })
+    }
+
     /// The coin type for ZEC, as defined by [SLIP 44].
     ///
     /// [SLIP 44]: https://github.com/satoshilabs/slips/blob/master/slip-0044.md
@@ -196,6 +241,7 @@ impl Parameters for MainNetwork {
             NetworkUpgrade::Blossom => Some(BlockHeight(10_000_000)),
             NetworkUpgrade::Heartwood => Some(BlockHeight(20_000_000)),
             NetworkUpgrade::Canopy => Some(BlockHeight(30_000_000)),
+            NetworkUpgrade::Nu5 => Some(BlockHeight(40_000_000)),
             #[cfg(feature = "zfuture")]
             NetworkUpgrade::ZFuture => None,
         }
@@ -241,6 +287,7 @@ impl Parameters for TestNetwork {
             NetworkUpgrade::Blossom => Some(BlockHeight(10_000_000)),
             NetworkUpgrade::Heartwood => Some(BlockHeight(20_000_000)),
             NetworkUpgrade::Canopy => Some(BlockHeight(30_000_000)),
+            NetworkUpgrade::Nu5 => Some(BlockHeight(40_000_000)),
             #[cfg(feature = "zfuture")]
             NetworkUpgrade::ZFuture => None,
         }
@@ -271,6 +318,197 @@ impl Parameters for TestNetwork {
     }
 }
 
+/// Configurable network parameters for use in regtest and other integration-testing
+/// scenarios, where every network upgrade's activation height is supplied by the
+/// caller instead of being hardcoded as it is for [`MainNetwork`] and [`TestNetwork`].
+///
+/// This makes it possible to construct a network on which, for example, every
+/// upgrade activates at height 1.
+#[cfg(feature = "local-consensus")]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct LocalNetwork {
+    pub overwinter: Option<BlockHeight>,
+    pub sapling: Option<BlockHeight>,
+    pub ycash: Option<BlockHeight>,
+    pub blossom: Option<BlockHeight>,
+    pub heartwood: Option<BlockHeight>,
+    pub canopy: Option<BlockHeight>,
+    pub nu5: Option<BlockHeight>,
+    #[cfg(feature = "zfuture")]
+    pub zfuture: Option<BlockHeight>,
+}
+
+#[cfg(feature = "local-consensus")]
+impl Parameters for LocalNetwork {
+    fn activation_height(&self, nu: NetworkUpgrade) -> Option<BlockHeight> {
+        match nu {
+            NetworkUpgrade::Overwinter => self.overwinter,
+            NetworkUpgrade::Sapling => self.sapling,
+            NetworkUpgrade::Ycash => self.ycash,
+            NetworkUpgrade::Blossom => self.blossom,
+            NetworkUpgrade::Heartwood => self.heartwood,
+            NetworkUpgrade::Canopy => self.canopy,
+            NetworkUpgrade::Nu5 => self.nu5,
+            #[cfg(feature = "zfuture")]
+            NetworkUpgrade::ZFuture => self.zfuture,
+        }
+    }
+
+    fn coin_type(&self) -> u32 {
+        constants::testnet::COIN_TYPE
+    }
+
+    fn hrp_sapling_extended_spending_key(&self) -> &str {
+        constants::testnet::HRP_SAPLING_EXTENDED_SPENDING_KEY
+    }
+
+    fn hrp_sapling_extended_full_viewing_key(&self) -> &str {
+        constants::testnet::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY
+    }
+
+    fn hrp_sapling_payment_address(&self) -> &str {
+        constants::testnet::HRP_SAPLING_PAYMENT_ADDRESS
+    }
+
+    fn b58_pubkey_address_prefix(&self) -> [u8; 2] {
+        constants::testnet::B58_PUBKEY_ADDRESS_PREFIX
+    }
+
+    fn b58_script_address_prefix(&self) -> [u8; 2] {
+        constants::testnet::B58_SCRIPT_ADDRESS_PREFIX
+    }
+}
+
+/// A data-driven implementation of [`Parameters`], decoupled from the [`MainNetwork`]
+/// and [`TestNetwork`] marker structs.
+///
+/// Unlike the marker structs, whose activation heights are baked into `match` arms at
+/// compile time, a `NetworkParameters` value holds its activation heights (along with
+/// its coin type and address prefixes) as plain data. This makes it possible to build
+/// custom chains, or testnets with patched activation heights, without editing this
+/// crate. Construct one with [`NetworkParameters::builder`], which starts from an
+/// existing network's parameters (following [Zebra]'s approach of attaching an
+/// `activation_heights` table to a network value) and lets individual upgrade heights
+/// be overridden.
+///
+/// [Zebra]: https://github.com/ZcashFoundation/zebra
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkParameters {
+    activation_heights: Vec<(NetworkUpgrade, Option<BlockHeight>)>,
+    /// The activation height of `NetworkUpgrade::ZFuture`, tracked separately because
+    /// that upgrade (unlike the others) has no fixed position in `UPGRADES_IN_ORDER`.
+    #[cfg(feature = "zfuture")]
+    zfuture_activation_height: Option<BlockHeight>,
+    coin_type: u32,
+    hrp_sapling_extended_spending_key: String,
+    hrp_sapling_extended_full_viewing_key: String,
+    hrp_sapling_payment_address: String,
+    b58_pubkey_address_prefix: [u8; 2],
+    b58_script_address_prefix: [u8; 2],
+}
+
+impl NetworkParameters {
+    /// Returns a builder preloaded with `base`'s activation heights, coin type, and
+    /// address prefixes, e.g. [`MAIN_NETWORK`] or [`TEST_NETWORK`].
+    pub fn builder<P: Parameters>(base: &P) -> NetworkParametersBuilder {
+        NetworkParametersBuilder {
+            params: NetworkParameters {
+                activation_heights: UPGRADES_IN_ORDER
+                    .iter()
+                    .map(|&nu| (nu, base.activation_height(nu)))
+                    .collect(),
+                #[cfg(feature = "zfuture")]
+                zfuture_activation_height: base.activation_height(NetworkUpgrade::ZFuture),
+                coin_type: base.coin_type(),
+                hrp_sapling_extended_spending_key: base
+                    .hrp_sapling_extended_spending_key()
+                    .to_owned(),
+                hrp_sapling_extended_full_viewing_key: base
+                    .hrp_sapling_extended_full_viewing_key()
+                    .to_owned(),
+                hrp_sapling_payment_address: base.hrp_sapling_payment_address().to_owned(),
+                b58_pubkey_address_prefix: base.b58_pubkey_address_prefix(),
+                b58_script_address_prefix: base.b58_script_address_prefix(),
+            },
+        }
+    }
+}
+
+impl Parameters for NetworkParameters {
+    fn activation_height(&self, nu: NetworkUpgrade) -> Option<BlockHeight> {
+        #[cfg(feature = "zfuture")]
+        if let NetworkUpgrade::ZFuture = nu {
+            return self.zfuture_activation_height;
+        }
+
+        self.activation_heights
+            .iter()
+            .find(|(upgrade, _)| *upgrade == nu)
+            .and_then(|(_, height)| *height)
+    }
+
+    fn coin_type(&self) -> u32 {
+        self.coin_type
+    }
+
+    fn hrp_sapling_extended_spending_key(&self) -> &str {
+        &self.hrp_sapling_extended_spending_key
+    }
+
+    fn hrp_sapling_extended_full_viewing_key(&self) -> &str {
+        &self.hrp_sapling_extended_full_viewing_key
+    }
+
+    fn hrp_sapling_payment_address(&self) -> &str {
+        &self.hrp_sapling_payment_address
+    }
+
+    fn b58_pubkey_address_prefix(&self) -> [u8; 2] {
+        self.b58_pubkey_address_prefix
+    }
+
+    fn b58_script_address_prefix(&self) -> [u8; 2] {
+        self.b58_script_address_prefix
+    }
+}
+
+/// Builder for [`NetworkParameters`], obtained via [`NetworkParameters::builder`].
+#[derive(Clone, Debug)]
+pub struct NetworkParametersBuilder {
+    params: NetworkParameters,
+}
+
+impl NetworkParametersBuilder {
+    /// Overrides the activation height of `nu`. Pass `None` to mark `nu` as not
+    /// activated.
+    pub fn with_activation_height(
+        mut self,
+        nu: NetworkUpgrade,
+        height: impl Into<Option<BlockHeight>>,
+    ) -> Self {
+        #[cfg(feature = "zfuture")]
+        if let NetworkUpgrade::ZFuture = nu {
+            self.params.zfuture_activation_height = height.into();
+            return self;
+        }
+
+        if let Some(entry) = self
+            .params
+            .activation_heights
+            .iter_mut()
+            .find(|(upgrade, _)| *upgrade == nu)
+        {
+            entry.1 = height.into();
+        }
+        self
+    }
+
+    /// Finalizes the builder, returning the resulting [`NetworkParameters`].
+    pub fn build(self) -> NetworkParameters {
+        self.params
+    }
+}
+
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum Network {
     MainNetwork,
@@ -332,7 +570,7 @@ impl Parameters for Network {
 /// consensus rules enforced by the network are altered.
 ///
 /// See [ZIP 200](https://zips.z.cash/zip-0200) for more details.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum NetworkUpgrade {
     /// The [Overwinter] network upgrade.
     ///
@@ -358,6 +596,10 @@ pub enum NetworkUpgrade {
     ///
     /// [Canopy]: https://z.cash/upgrade/canopy/
     Canopy,
+    /// The [Nu5] network upgrade.
+    ///
+    /// [Nu5]: https://z.cash/upgrade/nu5/
+    Nu5,
     /// The ZFUTURE network upgrade.
     ///
     /// This upgrade is expected never to activate on mainnet;
@@ -376,6 +618,7 @@ impl fmt::Display for NetworkUpgrade {
             NetworkUpgrade::Blossom => write!(f, "Blossom"),
             NetworkUpgrade::Heartwood => write!(f, "Heartwood"),
             NetworkUpgrade::Canopy => write!(f, "Canopy"),
+            NetworkUpgrade::Nu5 => write!(f, "Nu5"),
             #[cfg(feature = "zfuture")]
             NetworkUpgrade::ZFuture => write!(f, "ZFUTURE"),
         }
@@ -391,10 +634,16 @@ impl NetworkUpgrade {
             NetworkUpgrade::Blossom => BranchId::Blossom,
             NetworkUpgrade::Heartwood => BranchId::Heartwood,
             NetworkUpgrade::Canopy => BranchId::Canopy,
+            NetworkUpgrade::Nu5 => BranchId::Nu5,
             #[cfg(feature = "zfuture")]
             NetworkUpgrade::ZFuture => BranchId::ZFuture,
         }
     }
+
+    /// Returns an iterator over the network upgrades in order of activation.
+    pub fn iter() -> impl DoubleEndedIterator<Item = NetworkUpgrade> {
+        UPGRADES_IN_ORDER.iter().copied()
+    }
 }
 
 /// The network upgrades on the Zcash chain in order of activation.
@@ -408,6 +657,7 @@ const UPGRADES_IN_ORDER: &[NetworkUpgrade] = &[
     NetworkUpgrade::Blossom,
     NetworkUpgrade::Heartwood,
     NetworkUpgrade::Canopy,
+    NetworkUpgrade::Nu5,
 ];
 
 pub const ZIP212_GRACE_PERIOD: u32 = 32256;
@@ -441,6 +691,8 @@ pub enum BranchId {
     Heartwood,
     /// The consensus rules deployed by [`NetworkUpgrade::Canopy`].
     Canopy,
+    /// The consensus rules deployed by [`NetworkUpgrade::Nu5`].
+    Nu5,
     /// Candidates for future consensus rules; this branch will never
     /// activate on mainnet.
     #[cfg(feature = "zfuture")]
@@ -459,6 +711,7 @@ impl TryFrom<u32> for BranchId {
             0x2bb4_0e60 => Ok(BranchId::Blossom),
             0xf5b9_230b => Ok(BranchId::Heartwood),
             0xe9ff_75a6 => Ok(BranchId::Canopy),
+            0xc2d6_d0b4 => Ok(BranchId::Nu5),
             #[cfg(feature = "zfuture")]
             0xffff_ffff => Ok(BranchId::ZFuture),
             _ => Err("Unknown consensus branch ID"),
@@ -476,6 +729,7 @@ impl From<BranchId> for u32 {
             BranchId::Blossom => 0x2bb4_0e60,
             BranchId::Heartwood => 0xf5b9_230b,
             BranchId::Canopy => 0xe9ff_75a6,
+            BranchId::Nu5 => 0xc2d6_d0b4,
             #[cfg(feature = "zfuture")]
             BranchId::ZFuture => 0xffff_ffff,
         }
@@ -504,7 +758,8 @@ mod tests {
     use std::convert::TryFrom;
 
     use super::{
-        BlockHeight, BranchId, NetworkUpgrade, Parameters, MAIN_NETWORK, UPGRADES_IN_ORDER,
+        BlockHeight, BranchId, NetworkParameters, NetworkUpgrade, Parameters, MAIN_NETWORK,
+        UPGRADES_IN_ORDER,
     };
 
     #[test]
@@ -532,6 +787,132 @@ mod tests {
         assert!(MAIN_NETWORK.is_nu_active(NetworkUpgrade::Overwinter, BlockHeight(347_500)));
     }
 
+    #[cfg(feature = "local-consensus")]
+    #[test]
+    fn local_network_activates_every_upgrade_at_height_1() {
+        use super::LocalNetwork;
+
+        let local = LocalNetwork {
+            overwinter: Some(BlockHeight(1)),
+            sapling: Some(BlockHeight(1)),
+            ycash: Some(BlockHeight(1)),
+            blossom: Some(BlockHeight(1)),
+            heartwood: Some(BlockHeight(1)),
+            canopy: Some(BlockHeight(1)),
+            nu5: Some(BlockHeight(1)),
+            #[cfg(feature = "zfuture")]
+            zfuture: None,
+        };
+
+        assert!(!local.is_nu_active(NetworkUpgrade::Canopy, BlockHeight(0)));
+        assert!(local.is_nu_active(NetworkUpgrade::Canopy, BlockHeight(1)));
+        assert_eq!(
+            BranchId::for_height(&local, BlockHeight(1)),
+            BranchId::Nu5,
+        );
+    }
+
+    #[test]
+    fn network_upgrade_iter() {
+        assert_eq!(
+            NetworkUpgrade::iter().collect::<Vec<_>>(),
+            UPGRADES_IN_ORDER.to_vec(),
+        );
+    }
+
+    #[test]
+    fn next_activation() {
+        assert_eq!(
+            MAIN_NETWORK.next_activation(BlockHeight(0)),
+            Some((NetworkUpgrade::Overwinter, BlockHeight(347_500))),
+        );
+        assert_eq!(
+            MAIN_NETWORK.next_activation(BlockHeight(347_500)),
+            Some((NetworkUpgrade::Sapling, BlockHeight(419_200))),
+        );
+        assert_eq!(MAIN_NETWORK.next_activation(BlockHeight(40_000_000)), None);
+    }
+
+    #[test]
+    fn activation_range() {
+        use std::ops::{Bound, RangeBounds};
+
+        assert_eq!(
+            MAIN_NETWORK
+                .activation_range(NetworkUpgrade::Overwinter)
+                .map(|r| (r.start_bound().cloned(), r.end_bound().cloned())),
+            Some((
+                Bound::Included(BlockHeight(347_500)),
+                Bound::Excluded(BlockHeight(419_200)),
+            )),
+        );
+        assert_eq!(
+            MAIN_NETWORK
+                .activation_range(NetworkUpgrade::Canopy)
+                .map(|r| (r.start_bound().cloned(), r.end_bound().cloned())),
+            Some((
+                Bound::Included(BlockHeight(30_000_000)),
+                Bound::Excluded(BlockHeight(40_000_000)),
+            )),
+        );
+    }
+
+    #[cfg(feature = "zfuture")]
+    #[test]
+    fn activation_range_for_upgrade_outside_upgrades_in_order() {
+        use std::ops::{Bound, RangeBounds};
+
+        // ZFuture has no fixed position in `UPGRADES_IN_ORDER`, so there is never a
+        // "next" upgrade to derive an upper bound from: the range is always unbounded
+        // above, regardless of its own activation height.
+        let params = NetworkParameters::builder(&MAIN_NETWORK)
+            .with_activation_height(NetworkUpgrade::ZFuture, BlockHeight(1))
+            .build();
+
+        assert_eq!(
+            params
+                .activation_range(NetworkUpgrade::ZFuture)
+                .map(|r| (r.start_bound().cloned(), r.end_bound().cloned())),
+            Some((Bound::Included(BlockHeight(1)), Bound::Unbounded)),
+        );
+    }
+
+    #[test]
+    fn network_parameters_override_activation_height() {
+        let params = NetworkParameters::builder(&MAIN_NETWORK)
+            .with_activation_height(NetworkUpgrade::Canopy, BlockHeight(1))
+            .build();
+
+        assert_eq!(
+            params.activation_height(NetworkUpgrade::Canopy),
+            Some(BlockHeight(1)),
+        );
+        assert_eq!(
+            params.activation_height(NetworkUpgrade::Overwinter),
+            MAIN_NETWORK.activation_height(NetworkUpgrade::Overwinter),
+        );
+        assert_eq!(params.coin_type(), MAIN_NETWORK.coin_type());
+    }
+
+    #[cfg(feature = "zfuture")]
+    #[test]
+    fn network_parameters_override_zfuture_activation_height() {
+        let params = NetworkParameters::builder(&MAIN_NETWORK)
+            .with_activation_height(NetworkUpgrade::ZFuture, BlockHeight(1))
+            .build();
+
+        assert_eq!(
+            params.activation_height(NetworkUpgrade::ZFuture),
+            Some(BlockHeight(1)),
+        );
+
+        let cleared = NetworkParameters::builder(&params)
+            .with_activation_height(NetworkUpgrade::ZFuture, None)
+            .build();
+
+        assert_eq!(cleared.activation_height(NetworkUpgrade::ZFuture), None);
+    }
+
     #[test]
     fn branch_id_from_u32() {
         assert_eq!(BranchId::try_from(0), Ok(BranchId::Sprout));
@@ -555,7 +936,16 @@ mod tests {
         assert_eq!(
             BranchId::for_height(&MAIN_NETWORK, BlockHeight(570_000)),
             BranchId::Ycash,
-        );        /*
+        );
+        assert_eq!(
+            BranchId::for_height(&MAIN_NETWORK, BlockHeight(39_999_999)),
+            BranchId::Canopy,
+        );
+        assert_eq!(
+            BranchId::for_height(&MAIN_NETWORK, BlockHeight(40_000_000)),
+            BranchId::Nu5,
+        );
+        /*
         assert_eq!(
             BranchId::for_height(&MAIN_NETWORK, BlockHeight(903_000)),
             BranchId::Heartwood,